@@ -6,31 +6,26 @@ fn main() {
     let cache_path = cache_path();
     let mut cd = CacheDir::read_dir(&cache_path).unwrap();
     loop {
-        let elapsed = sleep_upto(5_000);
+        sleep_upto(5_000);
         println!("== {:?} ==", time::Instant::now());
         let new_cd = CacheDir::read_dir(&cache_path).unwrap();
-        for (field, value) in new_cd.iter() {
-            if field.metadata().is_flag_never() {
+        let diff = cd.diff(&new_cd);
+        for (field, value) in diff.iter() {
+            if value == 0 {
                 continue;
             }
-            let old = cd.get_field(field);
-            if value != old {
-                let diff = value - old;
-                println!(
-                    "{:?} {} -> {} ( ~{} @ {:.3}/sec )",
-                    field,
-                    old,
-                    value,
-                    diff,
-                    (diff as f64) / elapsed
-                );
-            }
+            println!(
+                "{:?} +{} ( {:.3}/sec )",
+                field,
+                value,
+                diff.rate_per_sec(field)
+            );
         }
         cd = new_cd;
     }
 }
 
-fn sleep_upto(t: u64) -> f64 {
+fn sleep_upto(t: u64) {
     let poll_duration = time::Duration::from_millis(t / 10);
     let duration = time::Duration::from_millis(t);
     let now = time::Instant::now();
@@ -40,8 +35,6 @@ fn sleep_upto(t: u64) -> f64 {
             break;
         }
     }
-    let e = now.elapsed();
-    (e.as_secs() as f64) + ((e.subsec_millis() as f64) / 1000.0)
 }
 
 fn cache_path() -> PathBuf {
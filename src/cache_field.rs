@@ -17,6 +17,8 @@ const FLAG_NONE: u8 = 0;
 const FLAG_NOZERO: u8 = 1;
 const FLAG_ALWAYS: u8 = 2;
 const FLAG_NEVER: u8 = 4;
+const FLAG_UNCACHEABLE: u8 = 8;
+const FLAG_ERROR: u8 = 16;
 
 #[cfg_attr(feature = "external_doc", doc(include = "CacheFieldMeta.md"))]
 #[cfg_attr(
@@ -39,6 +41,14 @@ impl CacheFieldMeta {
     pub(super) fn is_flag_never(&self) -> bool {
         self.flags & FLAG_NEVER == FLAG_NEVER
     }
+
+    pub(super) fn is_flag_uncacheable(&self) -> bool {
+        self.flags & FLAG_UNCACHEABLE == FLAG_UNCACHEABLE
+    }
+
+    pub(super) fn is_flag_error(&self) -> bool {
+        self.flags & FLAG_ERROR == FLAG_ERROR
+    }
 }
 
 #[cfg_attr(feature = "external_doc", doc(include = "CacheField.md"))]
@@ -114,6 +124,15 @@ pub enum CacheField {
     UnsupportedDirective = 30,
     /// Counter of when the stats were last zeroed
     ZeroTimeStamp        = 31,
+    /// Counter of hits served from remote storage (e.g. an `http`/`redis`
+    /// secondary storage backend)
+    RemoteStorageHit     = 32,
+    /// Counter of misses against remote storage
+    RemoteStorageMiss    = 33,
+    /// Counter of errors communicating with remote storage
+    RemoteStorageError   = 34,
+    /// Counter of timeouts communicating with remote storage
+    RemoteStorageTimeout = 35,
 }
 
 // 100.0,   1 -> Rolls over from 102399k to 100.0 mb
@@ -200,19 +219,19 @@ impl CacheField {
                 id:      "called_for_link",
                 message: "called for link",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_ALWAYS,
+                flags:   FLAG_ALWAYS | FLAG_UNCACHEABLE,
             },
             CacheField::PreProcessing => &CacheFieldMeta {
                 id:      "called_for_preprocessing",
                 message: "called for preprocessing",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_ALWAYS,
+                flags:   FLAG_ALWAYS | FLAG_UNCACHEABLE,
             },
             CacheField::Multiple => &CacheFieldMeta {
                 id:      "multiple_source_files",
                 message: "multiple source files",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::StdOut => &CacheFieldMeta {
                 id:      "compiler_produced_stdout",
@@ -242,67 +261,67 @@ impl CacheField {
                 id:      "internal_error",
                 message: "ccache internal error",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_ERROR,
             },
             CacheField::PreProcessor => &CacheFieldMeta {
                 id:      "preprocessor_error",
                 message: "preprocessor error",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_ERROR,
             },
             CacheField::CantUsePch => &CacheFieldMeta {
                 id:      "could_not_use_precompiled_header",
                 message: "can't use precompiled header",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::Compiler => &CacheFieldMeta {
                 id:      "could_not_find_compiler",
                 message: "couldn't find the compiler",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_ERROR,
             },
             CacheField::Missing => &CacheFieldMeta {
                 id:      "missing_cache_file",
                 message: "cache file missing",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_ERROR,
             },
             CacheField::Args => &CacheFieldMeta {
                 id:      "bad_compiler_arguments",
                 message: "bad compiler arguments",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::SourceLang => &CacheFieldMeta {
                 id:      "unsupported_source_language",
                 message: "unsupported source language",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::CompCheck => &CacheFieldMeta {
                 id:      "compiler_check_failed",
                 message: "compiler check failed",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_ERROR,
             },
             CacheField::ConfTest => &CacheFieldMeta {
                 id:      "autoconf_test",
                 message: "autoconf compile/link",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::UnsupportedOption => &CacheFieldMeta {
                 id:      "unsupported_compiler_option",
                 message: "unsupported compiler option",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::UnsupportedDirective => &CacheFieldMeta {
                 id:      "unsupported_code_directive",
                 message: "unsupported code directive",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::OutStdOut => &CacheFieldMeta {
                 id:      "output_to_stdout",
@@ -314,19 +333,19 @@ impl CacheField {
                 id:      "bad_output_file",
                 message: "could not write to output file",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_ERROR,
             },
             CacheField::NoInput => &CacheFieldMeta {
                 id:      "no_input_file",
                 message: "no input file",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_UNCACHEABLE,
             },
             CacheField::BadExtraFile => &CacheFieldMeta {
                 id:      "error_hashing_extra_file",
                 message: "error hashing extra file",
                 format:  CacheFieldFormat::None,
-                flags:   FLAG_NONE,
+                flags:   FLAG_ERROR,
             },
             CacheField::NumCleanUps => &CacheFieldMeta {
                 id:      "cleanups_performed",
@@ -358,6 +377,30 @@ impl CacheField {
                 format:  CacheFieldFormat::None,
                 flags:   FLAG_NOZERO | FLAG_NEVER,
             },
+            CacheField::RemoteStorageHit => &CacheFieldMeta {
+                id:      "remote_storage_hit",
+                message: "cache hit (remote)",
+                format:  CacheFieldFormat::None,
+                flags:   FLAG_ALWAYS,
+            },
+            CacheField::RemoteStorageMiss => &CacheFieldMeta {
+                id:      "remote_storage_miss",
+                message: "cache miss (remote)",
+                format:  CacheFieldFormat::None,
+                flags:   FLAG_ALWAYS,
+            },
+            CacheField::RemoteStorageError => &CacheFieldMeta {
+                id:      "remote_storage_error",
+                message: "remote storage error",
+                format:  CacheFieldFormat::None,
+                flags:   FLAG_ERROR,
+            },
+            CacheField::RemoteStorageTimeout => &CacheFieldMeta {
+                id:      "remote_storage_timeout",
+                message: "remote storage timeout",
+                format:  CacheFieldFormat::None,
+                flags:   FLAG_ERROR,
+            },
             CacheField::None => &CacheFieldMeta {
                 id:      "internal_none",
                 message: "(internal) none",
@@ -368,14 +411,49 @@ impl CacheField {
     }
 }
 
+/// The number of [u64] slots backing a [CacheFieldData], sized to the
+/// largest field count of any known [CacheVersion].
+pub(super) const MAX_FIELDS: usize = 36;
+
+#[cfg_attr(feature = "external_doc", doc(include = "CacheVersion.md"))]
+#[cfg_attr(
+    not(feature = "external_doc"),
+    doc = "Identifies a generation of ccache's on-disk stats counter set."
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheVersion {
+    /// The ccache 3.x counter set (32 fields, no remote-storage counters)
+    V3,
+    /// The ccache 4.x counter set (adds the `remote_storage_*` counters)
+    V4,
+}
+
+impl CacheVersion {
+    /// The newest counter set this crate knows how to read
+    pub const LATEST: CacheVersion = CacheVersion::V4;
+
+    /// Returns the [FIELD_DATA_ORDER]-style table of fields for this
+    /// version, in on-disk order
+    pub fn field_data_order(self) -> &'static [CacheField] {
+        match self {
+            CacheVersion::V3 => FIELD_DATA_ORDER_V3,
+            CacheVersion::V4 => FIELD_DATA_ORDER,
+        }
+    }
+}
+
 #[cfg_attr(feature = "external_doc", doc(include = "CacheFieldData.md"))]
 #[cfg_attr(
     not(feature = "external_doc"),
     doc = "A hash-like interface for accessing values using Enums as keys"
 )]
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct CacheFieldData {
-    items: [u64; 32],
+    items: [u64; MAX_FIELDS],
+}
+
+impl Default for CacheFieldData {
+    fn default() -> Self { Self { items: [0; MAX_FIELDS] } }
 }
 
 impl CacheFieldData {
@@ -401,8 +479,46 @@ impl CacheFieldData {
     pub fn get_field(&self, f: CacheField) -> u64 { self.items[f.as_usize()] }
 }
 
+/// Contains an array of [CacheField] in "data order" for [CacheVersion::V3],
+/// the ccache 3.x counter set this crate originally targeted
+pub const FIELD_DATA_ORDER_V3: &[CacheField] = &[
+    CacheField::None,
+    CacheField::StdOut,
+    CacheField::Status,
+    CacheField::Error,
+    CacheField::ToCache,
+    CacheField::PreProcessor,
+    CacheField::Compiler,
+    CacheField::Missing,
+    CacheField::CacheHitCpp,
+    CacheField::Args,
+    CacheField::Link,
+    CacheField::NumFiles,
+    CacheField::TotalSize,
+    CacheField::ObsoleteMaxFiles,
+    CacheField::ObsoleteMaxSize,
+    CacheField::SourceLang,
+    CacheField::BadOutputFile,
+    CacheField::NoInput,
+    CacheField::Multiple,
+    CacheField::ConfTest,
+    CacheField::UnsupportedOption,
+    CacheField::OutStdOut,
+    CacheField::CacheHitDir,
+    CacheField::NoOutput,
+    CacheField::EmptyOutput,
+    CacheField::BadExtraFile,
+    CacheField::CompCheck,
+    CacheField::CantUsePch,
+    CacheField::PreProcessing,
+    CacheField::NumCleanUps,
+    CacheField::UnsupportedDirective,
+    CacheField::ZeroTimeStamp,
+];
+
 /// Contains an array of [CacheField] in "data order" ( that is, the sequence
-/// they should appear in a cache stats file )
+/// they should appear in a cache stats file ) for [CacheVersion::V4], the
+/// latest counter set this crate knows about
 pub const FIELD_DATA_ORDER: &[CacheField] = &[
     CacheField::None,
     CacheField::StdOut,
@@ -436,6 +552,10 @@ pub const FIELD_DATA_ORDER: &[CacheField] = &[
     CacheField::NumCleanUps,
     CacheField::UnsupportedDirective,
     CacheField::ZeroTimeStamp,
+    CacheField::RemoteStorageHit,
+    CacheField::RemoteStorageMiss,
+    CacheField::RemoteStorageError,
+    CacheField::RemoteStorageTimeout,
 ];
 
 /// Contains an array of [CacheField] in "display order" ( that is, the
@@ -474,6 +594,10 @@ pub const FIELD_DISPLAY_ORDER: &[CacheField] = &[
     CacheField::ObsoleteMaxFiles,
     CacheField::ObsoleteMaxSize,
     CacheField::None,
+    CacheField::RemoteStorageHit,
+    CacheField::RemoteStorageMiss,
+    CacheField::RemoteStorageError,
+    CacheField::RemoteStorageTimeout,
 ];
 
 #[test]
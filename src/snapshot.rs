@@ -0,0 +1,154 @@
+//! Binary snapshot format backing [crate::CacheFieldCollection::write_snapshot]
+//! and [read_snapshot_mmap]: a fixed-size [SnapshotHeader] followed by a
+//! dense array of `u64` counters, meant to be mapped straight back in
+//! rather than re-parsed from ccache's per-subdir text stats files on
+//! every poll.
+
+use crate::{CacheField, ErrorKind};
+use std::{fs::File, mem, path::Path};
+
+/// Magic number identifying a [SnapshotHeader], chosen to be recognizable
+/// when eyeballing a hex dump (`b"CCS1"` read little-endian).
+pub(crate) const SNAPSHOT_MAGIC: u32 = u32::from_le_bytes(*b"CCS1");
+
+#[cfg_attr(feature = "external_doc", doc(include = "SnapshotHeader.md"))]
+#[cfg_attr(
+    not(feature = "external_doc"),
+    doc = "The fixed-size header at the start of a binary snapshot written \
+           by [crate::CacheFieldCollection::write_snapshot]."
+)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotHeader {
+    /// Identifies the file as a snapshot; see [SNAPSHOT_MAGIC]
+    pub magic:            u32,
+    /// The snapshot format version; currently always `1`
+    pub version:          u16,
+    /// The number of `u64` counters following this header
+    pub field_count:      u16,
+    /// The `stats_zeroed_timestamp` field recorded at snapshot time
+    pub zeroed_timestamp: u64,
+}
+
+#[cfg_attr(feature = "external_doc", doc(include = "CacheSnapshot.md"))]
+#[cfg_attr(
+    not(feature = "external_doc"),
+    doc = "A zero-copy view over a binary snapshot written by \
+           [crate::CacheFieldCollection::write_snapshot], backed by a \
+           memory-mapped file. See [read_snapshot_mmap]."
+)]
+pub struct CacheSnapshot {
+    mmap:        memmap2::Mmap,
+    field_count: usize,
+}
+
+impl CacheSnapshot {
+    /// Returns a value for the named field, read directly out of the
+    /// memory-mapped counters with no copy or parse step.
+    ///
+    /// Fields beyond what this snapshot's header declares (an older,
+    /// shorter-format snapshot read by a newer build) read back as `0`,
+    /// mirroring how [crate::CacheFieldData] treats a field it has never
+    /// seen set.
+    pub fn get_field(&self, f: CacheField) -> u64 {
+        self.counters().get(f.as_usize()).copied().unwrap_or(0)
+    }
+
+    /// Returns the `stats_zeroed_timestamp` recorded in the snapshot header
+    pub fn mtime(&self) -> chrono::DateTime<chrono::Utc> {
+        use chrono::TimeZone;
+        chrono::Utc.timestamp(self.header().zeroed_timestamp as i64, 0)
+    }
+
+    fn header(&self) -> &SnapshotHeader {
+        // Safety: `read_snapshot_mmap` already checked the mapping is at
+        // least `size_of::<SnapshotHeader>()` bytes, and a page-backed
+        // mmap is always aligned far more strictly than this header needs.
+        unsafe { &*(self.mmap.as_ptr() as *const SnapshotHeader) }
+    }
+
+    fn counters(&self) -> &[u64] {
+        let header_size = mem::size_of::<SnapshotHeader>();
+        // Safety: alignment was already asserted by `read_snapshot_mmap`;
+        // this just repeats the cast rather than stashing a
+        // self-referential slice on the struct.
+        let (prefix, counters, _) =
+            unsafe { self.mmap[header_size..].align_to::<u64>() };
+        debug_assert!(prefix.is_empty(), "mmap counters are misaligned");
+        &counters[..self.field_count]
+    }
+}
+
+/// Maps `path` and returns a [CacheSnapshot] view over it, validating the
+/// header's magic and the mapping's length and alignment up front, so
+/// later [CacheSnapshot::get_field] calls can trust the mapping and just
+/// read straight out of it.
+///
+/// Returns an [ErrorKind::IoError] wrapping
+/// [std::io::ErrorKind::UnexpectedEof] if the file is shorter than its own
+/// header claims, and an [ErrorKind::Stringy] if it doesn't look like a
+/// snapshot at all.
+pub fn read_snapshot_mmap<P: AsRef<Path>>(
+    path: P,
+) -> Result<CacheSnapshot, ErrorKind> {
+    let file = File::open(path)?;
+    // Safety: the mapping is only ever read through here, and the caller
+    // is trusted not to truncate the file out from under a live
+    // CacheSnapshot.
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+    let header_size = mem::size_of::<SnapshotHeader>();
+    if mmap.len() < header_size {
+        return Err(unexpected_eof());
+    }
+    let (prefix, header, _) =
+        unsafe { mmap[..header_size].align_to::<SnapshotHeader>() };
+    assert!(prefix.is_empty(), "mmap is not u64-aligned");
+    let header = &header[0];
+    if header.magic != SNAPSHOT_MAGIC {
+        return Err(ErrorKind::Stringy(format!(
+            "not a ccache snapshot file (expected magic {:#x}, found {:#x})",
+            SNAPSHOT_MAGIC, header.magic
+        )));
+    }
+
+    let field_count = header.field_count as usize;
+    if mmap.len() < header_size + field_count * mem::size_of::<u64>() {
+        return Err(unexpected_eof());
+    }
+    Ok(CacheSnapshot { mmap, field_count })
+}
+
+fn unexpected_eof() -> ErrorKind {
+    ErrorKind::IoError(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+}
+
+#[test]
+fn test_snapshot_round_trip() -> std::io::Result<()> {
+    use crate::{CacheFieldCollection, CacheLeaf};
+    use chrono::{TimeZone, Utc};
+
+    // One line per FIELD_DATA_ORDER slot: ToCache=3, CacheHitCpp=10,
+    // CacheHitDir=20, ZeroTimeStamp=123, the rest 0.
+    let stats = "0\n0\n0\n0\n3\n0\n0\n0\n10\n0\n0\n0\n0\n0\n0\n0\n0\n0\n\
+                 0\n0\n0\n0\n20\n0\n0\n0\n0\n0\n0\n0\n0\n123\n0\n0\n0\n0\n";
+    let leaf = CacheLeaf::from_reader(stats.as_bytes(), Utc.timestamp(123, 0))
+        .expect("well-formed synthetic stats text should always parse");
+
+    let path = std::env::temp_dir()
+        .join("ccache_stats_reader_test_snapshot_round_trip.bin");
+    let mut file = std::fs::File::create(&path)?;
+    leaf.write_snapshot(&mut file)
+        .expect("writing a snapshot to a fresh file should never fail");
+    drop(file);
+
+    let snapshot = read_snapshot_mmap(&path)
+        .expect("a snapshot this crate just wrote should read back");
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(snapshot.get_field(CacheField::ToCache), 3);
+    assert_eq!(snapshot.get_field(CacheField::CacheHitCpp), 10);
+    assert_eq!(snapshot.get_field(CacheField::CacheHitDir), 20);
+    assert_eq!(snapshot.mtime().timestamp(), 123);
+    Ok(())
+}